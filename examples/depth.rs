@@ -0,0 +1,61 @@
+//! A managed depth-stencil attachment that tracks the swapchain's size and
+//! sample count, so examples can opt into depth testing without having to
+//! keep a second texture in sync with `multisampled_framebuffer` by hand.
+//!
+//! A pipeline's color and depth attachments must share the same sample
+//! count, so [`create_depth_texture`] takes the same `sample_count` the
+//! example is using for its multisampled color target, and should be
+//! recreated alongside it on resize and on sample-count changes.
+
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Creates a depth-stencil texture sized to `sc_desc` and multisampled at
+/// `sample_count`, matching the example's color attachment.
+pub fn create_depth_texture(
+    device: &wgpu::Device,
+    sc_desc: &wgpu::SwapChainDescriptor,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("depth texture"),
+        size: wgpu::Extent3d {
+            width: sc_desc.width,
+            height: sc_desc.height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+    });
+    texture.create_default_view()
+}
+
+/// A standard depth-test-only `DepthStencilStateDescriptor` against
+/// `DEPTH_FORMAT`, with the stencil test disabled.
+pub fn depth_stencil_state() -> wgpu::DepthStencilStateDescriptor {
+    wgpu::DepthStencilStateDescriptor {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: true,
+        depth_compare: wgpu::CompareFunction::Less,
+        stencil: wgpu::StencilStateDescriptor::default(),
+    }
+}
+
+/// Builds the render pass attachment for `view`, clearing depth to the far
+/// plane (1.0) every pass. The stencil test is unused, but is still cleared
+/// since `DEPTH_FORMAT` carries no stencil aspect to diverge from it.
+pub fn depth_stencil_attachment(
+    view: &wgpu::TextureView,
+) -> wgpu::RenderPassDepthStencilAttachmentDescriptor<'_> {
+    wgpu::RenderPassDepthStencilAttachmentDescriptor {
+        attachment: view,
+        depth_load_op: wgpu::LoadOp::Clear,
+        depth_store_op: wgpu::StoreOp::Store,
+        clear_depth: 1.0,
+        stencil_load_op: wgpu::LoadOp::Clear,
+        stencil_store_op: wgpu::StoreOp::Store,
+        clear_stencil: 0,
+    }
+}
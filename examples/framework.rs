@@ -0,0 +1,213 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use winit::{
+    event::{self, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+};
+
+pub mod depth;
+pub mod post_process;
+pub mod staging_belt;
+pub mod stroke;
+pub mod text;
+
+fn noop_raw_waker() -> RawWaker {
+    fn no_op(_: *const ()) {}
+    fn clone(_: *const ()) -> RawWaker {
+        noop_raw_waker()
+    }
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(clone, no_op, no_op, no_op);
+    RawWaker::new(std::ptr::null(), &VTABLE)
+}
+
+/// Drives `future` to completion, calling `device.poll` between polls.
+///
+/// wgpu only fires a `map_async` callback (and so only wakes a future like
+/// [`staging_belt::StagingBelt::recall`]'s) in response to `Device::poll` on
+/// the native backends; nothing drives that on its own. Plain
+/// `futures::executor::block_on` would therefore hang forever on the first
+/// `recall` with chunks actually pending, since nothing would ever poll the
+/// device while it's blocked. Use this instead for any future that can only
+/// make progress via `device.poll`.
+pub fn block_on_device<F: Future>(device: &wgpu::Device, future: F) -> F::Output {
+    futures::pin_mut!(future);
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        device.poll(wgpu::Maintain::Poll);
+        if let Poll::Ready(output) = Pin::new(&mut future).poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+pub trait Example: 'static + Sized {
+    fn init(
+        sc_desc: &wgpu::SwapChainDescriptor,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> (Self, Option<wgpu::CommandBuffer>);
+
+    fn resize(
+        &mut self,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    );
+
+    fn update(&mut self, event: WindowEvent);
+
+    fn render(
+        &mut self,
+        frame: &wgpu::SwapChainTexture,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> wgpu::CommandBuffer;
+}
+
+struct Setup {
+    window: winit::window::Window,
+    event_loop: EventLoop<()>,
+    instance: wgpu::Instance,
+    size: winit::dpi::PhysicalSize<u32>,
+    surface: wgpu::Surface,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+async fn setup(title: &str) -> Setup {
+    let event_loop = EventLoop::new();
+    let window = winit::window::Window::new(&event_loop).unwrap();
+    window.set_title(title);
+    let size = window.inner_size();
+
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let surface = unsafe { instance.create_surface(&window) };
+
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::Default,
+            compatible_surface: Some(&surface),
+        })
+        .await
+        .expect("No suitable GPU adapters found on the system!");
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                extensions: wgpu::Extensions {
+                    anisotropic_filtering: false,
+                },
+                limits: wgpu::Limits::default(),
+                shader_validation: true,
+            },
+            None,
+        )
+        .await
+        .expect("Unable to find a suitable GPU device!");
+
+    Setup {
+        window,
+        event_loop,
+        instance,
+        size,
+        surface,
+        adapter,
+        device,
+        queue,
+    }
+}
+
+fn start<E: Example>(
+    Setup {
+        window,
+        event_loop,
+        instance: _,
+        size,
+        surface,
+        adapter: _,
+        device,
+        queue,
+    }: Setup,
+) {
+    let mut sc_desc = wgpu::SwapChainDescriptor {
+        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        width: size.width,
+        height: size.height,
+        present_mode: wgpu::PresentMode::Mailbox,
+    };
+    let mut swap_chain = device.create_swap_chain(&surface, &sc_desc);
+
+    log::info!("Initializing the example...");
+    let (mut example, init_command_buf) = E::init(&sc_desc, &device, &queue);
+    if let Some(command_buf) = init_command_buf {
+        queue.submit(Some(command_buf));
+    }
+
+    log::info!("Entering render loop...");
+    event_loop.run(move |event, _, control_flow| {
+        let _ = &window;
+        *control_flow = ControlFlow::Poll;
+        match event {
+            event::Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            } => {
+                sc_desc.width = size.width.max(1);
+                sc_desc.height = size.height.max(1);
+                swap_chain = device.create_swap_chain(&surface, &sc_desc);
+                example.resize(&sc_desc, &device, &queue);
+            }
+            event::Event::WindowEvent { event, .. } => match event {
+                WindowEvent::KeyboardInput {
+                    input:
+                        event::KeyboardInput {
+                            virtual_keycode: Some(event::VirtualKeyCode::Escape),
+                            state: event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                }
+                | WindowEvent::CloseRequested => {
+                    *control_flow = ControlFlow::Exit;
+                }
+                _ => {
+                    example.update(event);
+                }
+            },
+            event::Event::RedrawRequested(_) => {
+                let frame = match swap_chain.get_current_frame() {
+                    Ok(frame) => frame,
+                    Err(_) => {
+                        swap_chain = device.create_swap_chain(&surface, &sc_desc);
+                        swap_chain
+                            .get_current_frame()
+                            .expect("Failed to acquire next swap chain texture!")
+                    }
+                };
+
+                let command_buf = example.render(&frame.output, &device, &queue);
+                queue.submit(Some(command_buf));
+                // Routine upkeep: drops resources whose work has completed.
+                // Examples that need to *wait* on a `map_async` callback
+                // (e.g. `StagingBelt::recall`) can't rely on this running in
+                // time to unblock them and should poll via
+                // `block_on_device` instead.
+                device.poll(wgpu::Maintain::Poll);
+            }
+            event::Event::MainEventsCleared => {
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
+
+pub fn run<E: Example>(title: &str) {
+    let setup = futures::executor::block_on(setup(title));
+    start::<E>(setup);
+}
@@ -3,28 +3,45 @@
 //! *    A new texture with a sample_count > 1 is created and set as the color_attachment instead of the swapchain.
 //! *    The swapchain is now specified as a resolve_target.
 //!
-//! The parts of this example enabling LineList are:
-//! *   Set the primitive_topology to PrimitiveTopology::LineList.
-//! *   Vertices and Indices describe the two points that make up a line.
+//! The parts of this example enabling the thick lines are:
+//! *   Each spoke is tessellated on the CPU into a triangle strip via
+//!     `framework::stroke`, instead of being drawn as a single `LineList` segment.
+//! *   Set the primitive_topology to PrimitiveTopology::TriangleList.
+//! *   A per-vertex `edge_distance` attribute lets the fragment shader apply
+//!     analytic coverage AA across the width of the stroke.
+//!
+//! The wheel is animated every frame by re-tessellating it at a rotated
+//! angle and streaming the new vertex data into the (never reallocated)
+//! vertex buffer through a `framework::staging_belt::StagingBelt`.
+//!
+//! The current `sample_count` is drawn as a HUD overlay in the corner via
+//! `framework::text`, instead of only being logged.
+//!
+//! Pressing `D` toggles an optional depth-tested mode (`framework::depth`):
+//! each spoke is assigned one of two depths, so whichever spoke should win
+//! at the shared hub is decided by the depth test instead of draw order.
+//! The depth texture is recreated at the same sample count as the color
+//! target everywhere the color target itself is recreated, since the two
+//! attachments must match.
+//!
+//! The scene (wheel + HUD text) is resolved into an offscreen target
+//! instead of the swapchain directly, and a two-pass bloom
+//! (`framework::post_process`) then blurs it and adds the result back on
+//! top before the final pass reaches the swapchain.
 
 #[path = "../framework.rs"]
 mod framework;
 
 use std::iter;
 
-use bytemuck::{Pod, Zeroable};
-
+use framework::depth;
+use framework::post_process::{OutputScale, PassDesc, PostProcessChain, Preset};
+use framework::staging_belt::StagingBelt;
+use framework::stroke::{self, LineCap, LineJoin, StrokeMesh, StrokeOptions, StrokePoint, StrokeVertex};
+use framework::text::{Section, TextRenderer};
 use wgpu::vertex_attr_array;
 
-#[repr(C)]
-#[derive(Clone, Copy)]
-struct Vertex {
-    _pos: [f32; 2],
-    _color: [f32; 4],
-}
-
-unsafe impl Pod for Vertex {}
-unsafe impl Zeroable for Vertex {}
+static FONT_BYTES: &[u8] = include_bytes!("../DejaVuSans.ttf");
 
 struct Example {
     bundle: wgpu::RenderBundle,
@@ -32,13 +49,41 @@ struct Example {
     fs_module: wgpu::ShaderModule,
     pipeline_layout: wgpu::PipelineLayout,
     multisampled_framebuffer: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+    depth_enabled: bool,
     vertex_buffer: wgpu::Buffer,
-    vertex_count: u32,
+    vertex_buffer_size: wgpu::BufferAddress,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+    staging_belt: StagingBelt,
+    text_renderer: TextRenderer,
+    post_process: PostProcessChain,
+    angle: f32,
     sample_count: u32,
     rebuild_bundle: bool,
     sc_desc: wgpu::SwapChainDescriptor,
 }
 
+/// A two-pass separable bloom: a horizontal blur downsampled to half
+/// resolution feeds a vertical blur, which adds the result back onto the
+/// full-resolution scene for the final composite.
+fn bloom_preset() -> Preset {
+    Preset {
+        passes: vec![
+            PassDesc {
+                fragment_shader: include_bytes!("bloom_h.frag.spv"),
+                scale: OutputScale::Relative(0.5),
+                filter_mode: wgpu::FilterMode::Linear,
+            },
+            PassDesc {
+                fragment_shader: include_bytes!("bloom_v.frag.spv"),
+                scale: OutputScale::Relative(1.0),
+                filter_mode: wgpu::FilterMode::Linear,
+            },
+        ],
+    }
+}
+
 impl Example {
     fn create_bundle(
         device: &wgpu::Device,
@@ -47,10 +92,12 @@ impl Example {
         fs_module: &wgpu::ShaderModule,
         pipeline_layout: &wgpu::PipelineLayout,
         sample_count: u32,
+        depth_enabled: bool,
         vertex_buffer: &wgpu::Buffer,
-        vertex_count: u32,
+        index_buffer: &wgpu::Buffer,
+        index_count: u32,
     ) -> wgpu::RenderBundle {
-        log::info!("sample_count: {}", sample_count);
+        log::info!("sample_count: {}, depth_enabled: {}", sample_count, depth_enabled);
         let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             layout: &pipeline_layout,
             vertex_stage: wgpu::ProgrammableStageDescriptor {
@@ -68,20 +115,36 @@ impl Example {
                 depth_bias_slope_scale: 0.0,
                 depth_bias_clamp: 0.0,
             }),
-            primitive_topology: wgpu::PrimitiveTopology::LineList,
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            // `edge_distance`'s coverage ends up in `o_color.a`; it needs
+            // actual alpha blending (as `text.rs` uses for the same kind of
+            // coverage-based edge) or the analytic AA it's meant to drive
+            // has no visual effect.
             color_states: &[wgpu::ColorStateDescriptor {
                 format: sc_desc.format,
-                color_blend: wgpu::BlendDescriptor::REPLACE,
-                alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                color_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
                 write_mask: wgpu::ColorWrite::ALL,
             }],
-            depth_stencil_state: None,
+            depth_stencil_state: if depth_enabled {
+                Some(depth::depth_stencil_state())
+            } else {
+                None
+            },
             vertex_state: wgpu::VertexStateDescriptor {
                 index_format: wgpu::IndexFormat::Uint16,
                 vertex_buffers: &[wgpu::VertexBufferDescriptor {
-                    stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    stride: std::mem::size_of::<StrokeVertex>() as wgpu::BufferAddress,
                     step_mode: wgpu::InputStepMode::Vertex,
-                    attributes: &vertex_attr_array![0 => Float2, 1 => Float4],
+                    attributes: &vertex_attr_array![0 => Float2, 1 => Float4, 2 => Float, 3 => Float],
                 }],
             },
             sample_count,
@@ -92,12 +155,17 @@ impl Example {
             device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
                 label: None,
                 color_formats: &[sc_desc.format],
-                depth_stencil_format: None,
+                depth_stencil_format: if depth_enabled {
+                    Some(depth::DEPTH_FORMAT)
+                } else {
+                    None
+                },
                 sample_count,
             });
         encoder.set_pipeline(&pipeline);
         encoder.set_vertex_buffer(0, vertex_buffer.slice(..));
-        encoder.draw(0..vertex_count, 0..1);
+        encoder.set_index_buffer(index_buffer.slice(..));
+        encoder.draw_indexed(0..index_count, 0, 0..1);
         encoder.finish(&wgpu::RenderBundleDescriptor {
             label: Some("main"),
         })
@@ -127,6 +195,64 @@ impl Example {
             .create_texture(multisampled_frame_descriptor)
             .create_default_view()
     }
+
+    /// Builds the spoke-wheel pattern, rotated by `angle` radians, as a set
+    /// of thick strokes. Each spoke is its own two-point polyline, so only
+    /// the round caps are exercised here, but `tessellate_stroke` is the
+    /// same entry point a multi-point polyline would use. The vertex count
+    /// (and therefore the mesh's byte size) never changes between calls, so
+    /// the result can always be streamed into the same vertex buffer.
+    ///
+    /// Spokes alternate between two depths so that, with depth testing on,
+    /// every other spoke wins at the shared hub regardless of draw order;
+    /// with it off, the last spoke drawn always wins instead.
+    ///
+    /// A single rim polyline threads through every spoke tip with a
+    /// `LineJoin::Round` join at each one, so the join code (otherwise
+    /// unused, since every spoke is just a 2-point segment) is actually
+    /// exercised.
+    fn build_mesh(angle: f32) -> StrokeMesh {
+        let mut mesh = StrokeMesh::default();
+        let options = StrokeOptions {
+            width: 0.03,
+            start_cap: LineCap::Round,
+            end_cap: LineCap::Round,
+            ..StrokeOptions::default()
+        };
+
+        let max = 50;
+        let mut rim = Vec::with_capacity(max + 1);
+        for i in 0..max {
+            let percent = i as f32 / max as f32;
+            let (sin, cos) = (percent * 2.0 * std::f32::consts::PI + angle).sin_cos();
+            let depth = if i % 2 == 0 { 0.4 } else { 0.6 };
+            let spoke = [
+                StrokePoint {
+                    position: [0.0, 0.0],
+                    color: [1.0, -sin, cos, 1.0],
+                    depth,
+                },
+                StrokePoint {
+                    position: [cos, sin],
+                    color: [sin, -cos, 1.0, 1.0],
+                    depth,
+                },
+            ];
+            rim.push(spoke[1]);
+            stroke::tessellate_stroke(&spoke, &options, &mut mesh);
+        }
+        rim.push(rim[0]);
+
+        let rim_options = StrokeOptions {
+            width: 0.015,
+            join: LineJoin::Round,
+            start_cap: LineCap::Butt,
+            end_cap: LineCap::Butt,
+        };
+        stroke::tessellate_stroke(&rim, &rim_options, &mut mesh);
+
+        mesh
+    }
 }
 
 impl framework::Example for Example {
@@ -135,7 +261,7 @@ impl framework::Example for Example {
         device: &wgpu::Device,
         _queue: &wgpu::Queue,
     ) -> (Self, Option<wgpu::CommandBuffer>) {
-        log::info!("Press left/right arrow keys to change sample_count.");
+        log::info!("Press left/right arrow keys to change sample_count, D to toggle depth testing.");
         let sample_count = 4;
 
         let vs_bytes = include_bytes!("shader.vert.spv");
@@ -151,28 +277,32 @@ impl framework::Example for Example {
 
         let multisampled_framebuffer =
             Example::create_multisampled_framebuffer(device, sc_desc, sample_count);
+        let depth_view = depth::create_depth_texture(device, sc_desc, sample_count);
 
-        let mut vertex_data = vec![];
-
-        let max = 50;
-        for i in 0..max {
-            let percent = i as f32 / max as f32;
-            let (sin, cos) = (percent * 2.0 * std::f32::consts::PI).sin_cos();
-            vertex_data.push(Vertex {
-                _pos: [0.0, 0.0],
-                _color: [1.0, -sin, cos, 1.0],
-            });
-            vertex_data.push(Vertex {
-                _pos: [1.0 * cos, 1.0 * sin],
-                _color: [sin, -cos, 1.0, 1.0],
-            });
-        }
+        let mesh = Example::build_mesh(0.0);
+        let vertex_buffer_size =
+            (mesh.vertices.len() * std::mem::size_of::<StrokeVertex>()) as wgpu::BufferAddress;
 
         let vertex_buffer = device.create_buffer_with_data(
-            bytemuck::cast_slice(&vertex_data),
-            wgpu::BufferUsage::VERTEX,
+            bytemuck::cast_slice(&mesh.vertices),
+            wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        );
+        let index_buffer = device.create_buffer_with_data(
+            bytemuck::cast_slice(&mesh.indices),
+            wgpu::BufferUsage::INDEX,
+        );
+        let index_count = mesh.indices.len() as u32;
+
+        let text_renderer = TextRenderer::new(device, sc_desc.format, FONT_BYTES);
+
+        let post_process = PostProcessChain::new(
+            device,
+            &bloom_preset(),
+            sc_desc.format,
+            sc_desc.format,
+            (sc_desc.width, sc_desc.height),
+            (sc_desc.width, sc_desc.height),
         );
-        let vertex_count = vertex_data.len() as u32;
 
         let bundle = Example::create_bundle(
             device,
@@ -181,8 +311,10 @@ impl framework::Example for Example {
             &fs_module,
             &pipeline_layout,
             sample_count,
+            false,
             &vertex_buffer,
-            vertex_count,
+            &index_buffer,
+            index_count,
         );
 
         let this = Example {
@@ -191,8 +323,16 @@ impl framework::Example for Example {
             fs_module,
             pipeline_layout,
             multisampled_framebuffer,
+            depth_view,
+            depth_enabled: false,
             vertex_buffer,
-            vertex_count,
+            vertex_buffer_size,
+            index_buffer,
+            index_count,
+            staging_belt: StagingBelt::new(vertex_buffer_size),
+            text_renderer,
+            post_process,
+            angle: 0.0,
             sample_count,
             rebuild_bundle: false,
             sc_desc: sc_desc.clone(),
@@ -217,6 +357,10 @@ impl framework::Example for Example {
                                 self.rebuild_bundle = true;
                             }
                         }
+                        Some(winit::event::VirtualKeyCode::D) => {
+                            self.depth_enabled = !self.depth_enabled;
+                            self.rebuild_bundle = true;
+                        }
                         _ => {}
                     }
                 }
@@ -234,14 +378,30 @@ impl framework::Example for Example {
         self.sc_desc = sc_desc.clone();
         self.multisampled_framebuffer =
             Example::create_multisampled_framebuffer(device, sc_desc, self.sample_count);
+        self.depth_view = depth::create_depth_texture(device, sc_desc, self.sample_count);
+        self.post_process = PostProcessChain::new(
+            device,
+            &bloom_preset(),
+            sc_desc.format,
+            sc_desc.format,
+            (sc_desc.width, sc_desc.height),
+            (sc_desc.width, sc_desc.height),
+        );
     }
 
     fn render(
         &mut self,
         frame: &wgpu::SwapChainTexture,
         device: &wgpu::Device,
-        _queue: &wgpu::Queue,
+        queue: &wgpu::Queue,
     ) -> wgpu::CommandBuffer {
+        // Recycle the chunks used by the previous frame's write now that its
+        // command buffer has had a chance to be submitted and drained.
+        // `recall`'s future only progresses via `device.poll`, which nothing
+        // else pumps while we're blocked here, so drive it with
+        // `block_on_device` rather than a plain `block_on`.
+        framework::block_on_device(device, self.staging_belt.recall());
+
         if self.rebuild_bundle {
             self.bundle = Example::create_bundle(
                 device,
@@ -250,20 +410,53 @@ impl framework::Example for Example {
                 &self.fs_module,
                 &self.pipeline_layout,
                 self.sample_count,
+                self.depth_enabled,
                 &self.vertex_buffer,
-                self.vertex_count,
+                &self.index_buffer,
+                self.index_count,
             );
             self.multisampled_framebuffer =
                 Example::create_multisampled_framebuffer(device, &self.sc_desc, self.sample_count);
+            self.depth_view = depth::create_depth_texture(device, &self.sc_desc, self.sample_count);
             self.rebuild_bundle = false;
         }
 
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        self.angle += 0.01;
+        let mesh = Example::build_mesh(self.angle);
+        {
+            let mut vertex_view = self.staging_belt.write_buffer(
+                &mut encoder,
+                &self.vertex_buffer,
+                0,
+                self.vertex_buffer_size,
+                device,
+            );
+            vertex_view.copy_from_slice(bytemuck::cast_slice(&mesh.vertices));
+        }
+
+        self.text_renderer.queue(Section {
+            text: &format!(
+                "sample_count: {}, depth test: {}",
+                self.sample_count,
+                if self.depth_enabled { "on" } else { "off" }
+            ),
+            position: [10.0, 10.0],
+            color: [1.0, 1.0, 1.0, 1.0],
+            scale: 24.0,
+        });
+
+        // The scene renders into the post-process chain's scratch target
+        // instead of the swapchain directly, so the bloom passes have an
+        // "Original" image to blur and recombine before the swapchain is
+        // touched.
+        let scene_target = self.post_process.scene_target();
         {
             let rpass_color_attachment = if self.sample_count == 1 {
                 wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
+                    attachment: scene_target,
                     resolve_target: None,
                     load_op: wgpu::LoadOp::Clear,
                     store_op: wgpu::StoreOp::Store,
@@ -272,21 +465,39 @@ impl framework::Example for Example {
             } else {
                 wgpu::RenderPassColorAttachmentDescriptor {
                     attachment: &self.multisampled_framebuffer,
-                    resolve_target: Some(&frame.view),
+                    resolve_target: Some(scene_target),
                     load_op: wgpu::LoadOp::Clear,
                     store_op: wgpu::StoreOp::Store,
                     clear_color: wgpu::Color::BLACK,
                 }
             };
 
+            let rpass_depth_stencil_attachment = if self.depth_enabled {
+                Some(depth::depth_stencil_attachment(&self.depth_view))
+            } else {
+                None
+            };
+
             encoder
                 .begin_render_pass(&wgpu::RenderPassDescriptor {
                     color_attachments: &[rpass_color_attachment],
-                    depth_stencil_attachment: None,
+                    depth_stencil_attachment: rpass_depth_stencil_attachment,
                 })
                 .execute_bundles(iter::once(&self.bundle));
         }
 
+        self.text_renderer.draw(
+            &mut encoder,
+            device,
+            queue,
+            &mut self.staging_belt,
+            scene_target,
+            [self.sc_desc.width as f32, self.sc_desc.height as f32],
+        );
+        self.staging_belt.finish();
+
+        self.post_process.render(&mut encoder, queue, &frame.view);
+
         encoder.finish()
     }
 }
@@ -0,0 +1,382 @@
+//! A multi-pass post-processing chain driven by a declarative [`Preset`],
+//! modeled on shader-preset style filter chains: each [`PassDesc`] names a
+//! fragment shader, an output size (relative to the source or absolute) and
+//! a filter mode, and passes are run in order with the previous pass's
+//! output bound as `t_previous`/`s_previous` and the untouched scene bound
+//! as `t_original`/`s_original`, so later passes can combine the two (e.g.
+//! additive bloom).
+//!
+//! The scene should be rendered into [`PostProcessChain::scene_target`]
+//! instead of the swapchain view directly; [`PostProcessChain::render`]
+//! then runs the chain, with the final pass targeting whatever view is
+//! passed to it (typically the swapchain).
+
+use bytemuck::{Pod, Zeroable};
+
+/// How a pass's output texture is sized.
+#[derive(Clone, Copy)]
+pub enum OutputScale {
+    /// A multiple of the source (scene) resolution.
+    Relative(f32),
+    /// An exact pixel size.
+    Absolute { width: u32, height: u32 },
+}
+
+/// One pass of a [`Preset`].
+pub struct PassDesc {
+    /// Compiled SPIR-V for the pass's fragment shader. Reads `Locals` at
+    /// binding 0, `t_previous`/`s_previous` at bindings 1-2 and
+    /// `t_original`/`s_original` at bindings 3-4.
+    pub fragment_shader: &'static [u8],
+    pub scale: OutputScale,
+    pub filter_mode: wgpu::FilterMode,
+}
+
+/// A filter chain: an ordered list of passes, the last of which targets the
+/// view passed to [`PostProcessChain::render`] rather than its own texture.
+pub struct Preset {
+    pub passes: Vec<PassDesc>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PassUniforms {
+    output_size: [f32; 2],
+    source_size: [f32; 2],
+    frame: u32,
+    _padding: [u32; 3],
+}
+
+unsafe impl Pod for PassUniforms {}
+unsafe impl Zeroable for PassUniforms {}
+
+/// A texture a pass reads from or writes to, paired with the sampler that
+/// should be used to read it (its filter mode is a property of the producer,
+/// not the consumer).
+struct Resource {
+    view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    width: u32,
+    height: u32,
+}
+
+impl Resource {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        filter_mode: wgpu::FilterMode,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("post process pass output"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter_mode,
+            min_filter: filter_mode,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            compare: None,
+            anisotropy_clamp: None,
+            label: None,
+        });
+        Resource {
+            view: texture.create_default_view(),
+            sampler,
+            width,
+            height,
+        }
+    }
+}
+
+struct Pass {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    /// `None` for the final pass, which targets the view given to `render`.
+    output: Option<Resource>,
+    source_size: (u32, u32),
+}
+
+/// The runtime state for a [`Preset`]: the allocated intermediate textures
+/// and one pipeline/bind group per pass.
+pub struct PostProcessChain {
+    original: Resource,
+    passes: Vec<Pass>,
+    target_size: (u32, u32),
+    frame: u32,
+}
+
+fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: None,
+                },
+            ),
+            wgpu::BindGroupLayoutEntry::new(
+                1,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::SampledTexture {
+                    dimension: wgpu::TextureViewDimension::D2,
+                    component_type: wgpu::TextureComponentType::Float,
+                    multisampled: false,
+                },
+            ),
+            wgpu::BindGroupLayoutEntry::new(
+                2,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::Sampler { comparison: false },
+            ),
+            wgpu::BindGroupLayoutEntry::new(
+                3,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::SampledTexture {
+                    dimension: wgpu::TextureViewDimension::D2,
+                    component_type: wgpu::TextureComponentType::Float,
+                    multisampled: false,
+                },
+            ),
+            wgpu::BindGroupLayoutEntry::new(
+                4,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::Sampler { comparison: false },
+            ),
+        ],
+    })
+}
+
+fn bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    previous: &Resource,
+    original: &Resource,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[
+            wgpu::Binding {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+            },
+            wgpu::Binding {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&previous.view),
+            },
+            wgpu::Binding {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&previous.sampler),
+            },
+            wgpu::Binding {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&original.view),
+            },
+            wgpu::Binding {
+                binding: 4,
+                resource: wgpu::BindingResource::Sampler(&original.sampler),
+            },
+        ],
+    })
+}
+
+impl PostProcessChain {
+    /// Builds the chain described by `preset`. `source_size` is the
+    /// resolution the scene itself renders at; `target_format`/`target_size`
+    /// describe the view the final pass will draw into.
+    pub fn new(
+        device: &wgpu::Device,
+        preset: &Preset,
+        source_format: wgpu::TextureFormat,
+        target_format: wgpu::TextureFormat,
+        source_size: (u32, u32),
+        target_size: (u32, u32),
+    ) -> Self {
+        let vs_bytes = include_bytes!("post_process.vert.spv");
+        let vs_module = device
+            .create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(&vs_bytes[..])).unwrap());
+
+        let original = Resource::new(
+            device,
+            source_format,
+            source_size.0,
+            source_size.1,
+            wgpu::FilterMode::Linear,
+        );
+
+        let layout = bind_group_layout(device);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: &[&layout],
+        });
+
+        // Output textures are allocated up front (indexed the same as
+        // `preset.passes`, `None` for the final pass) so the bind group for
+        // pass `i` can simply borrow `outputs[i - 1]` without fighting the
+        // borrow checker over a `Vec` it's still being pushed into.
+        let pass_count = preset.passes.len();
+        let mut outputs: Vec<Option<Resource>> = Vec::with_capacity(pass_count);
+        for (i, desc) in preset.passes.iter().enumerate() {
+            let is_final = i + 1 == pass_count;
+            if is_final {
+                outputs.push(None);
+                continue;
+            }
+            let (width, height) = match desc.scale {
+                OutputScale::Relative(scale) => (
+                    (source_size.0 as f32 * scale).round().max(1.0) as u32,
+                    (source_size.1 as f32 * scale).round().max(1.0) as u32,
+                ),
+                OutputScale::Absolute { width, height } => (width, height),
+            };
+            outputs.push(Some(Resource::new(
+                device,
+                target_format,
+                width,
+                height,
+                desc.filter_mode,
+            )));
+        }
+
+        let mut passes = Vec::with_capacity(pass_count);
+        for (i, desc) in preset.passes.iter().enumerate() {
+            let is_final = i + 1 == pass_count;
+
+            let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("post process pass uniforms"),
+                size: std::mem::size_of::<PassUniforms>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let (pass_bind_group, source_size) = {
+                let previous: &Resource = if i == 0 {
+                    &original
+                } else {
+                    outputs[i - 1].as_ref().unwrap()
+                };
+                let pass_bind_group = bind_group(device, &layout, &uniform_buffer, previous, &original);
+                (pass_bind_group, (previous.width, previous.height))
+            };
+
+            let fs_module = device.create_shader_module(
+                &wgpu::read_spirv(std::io::Cursor::new(desc.fragment_shader)).unwrap(),
+            );
+            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                layout: &pipeline_layout,
+                vertex_stage: wgpu::ProgrammableStageDescriptor {
+                    module: &vs_module,
+                    entry_point: "main",
+                },
+                fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                    module: &fs_module,
+                    entry_point: "main",
+                }),
+                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: wgpu::CullMode::None,
+                    depth_bias: 0,
+                    depth_bias_slope_scale: 0.0,
+                    depth_bias_clamp: 0.0,
+                }),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+                color_states: &[wgpu::ColorStateDescriptor {
+                    format: target_format,
+                    color_blend: wgpu::BlendDescriptor::REPLACE,
+                    alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[],
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            });
+
+            passes.push(Pass {
+                pipeline,
+                bind_group: pass_bind_group,
+                uniform_buffer,
+                output: outputs[i].take(),
+                source_size,
+            });
+        }
+
+        PostProcessChain {
+            original,
+            passes,
+            target_size,
+            frame: 0,
+        }
+    }
+
+    /// The view the scene should be rendered into, in place of the
+    /// swapchain, so the chain has an "Original" source to bind to passes.
+    pub fn scene_target(&self) -> &wgpu::TextureView {
+        &self.original.view
+    }
+
+    /// Runs every pass in order, with the last pass's output going to
+    /// `target` (typically the current swapchain view).
+    pub fn render(&mut self, encoder: &mut wgpu::CommandEncoder, queue: &wgpu::Queue, target: &wgpu::TextureView) {
+        self.frame = self.frame.wrapping_add(1);
+        let pass_count = self.passes.len();
+        for (i, pass) in self.passes.iter().enumerate() {
+            let is_final = i + 1 == pass_count;
+            let output_view = if is_final {
+                target
+            } else {
+                &pass.output.as_ref().unwrap().view
+            };
+            let output_size = if is_final {
+                self.target_size
+            } else {
+                let output = pass.output.as_ref().unwrap();
+                (output.width, output.height)
+            };
+
+            let uniforms = PassUniforms {
+                output_size: [output_size.0 as f32, output_size.1 as f32],
+                source_size: [pass.source_size.0 as f32, pass.source_size.1 as f32],
+                frame: self.frame,
+                _padding: [0; 3],
+            };
+            queue.write_buffer(&pass.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: output_view,
+                    resolve_target: None,
+                    load_op: wgpu::LoadOp::Clear,
+                    store_op: wgpu::StoreOp::Store,
+                    clear_color: wgpu::Color::BLACK,
+                }],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&pass.pipeline);
+            rpass.set_bind_group(0, &pass.bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! A ring of reusable, persistently-allocated upload buffers for streaming
+//! per-frame data into GPU buffers without reallocating a new buffer (and
+//! re-copying the old contents) every time the data changes.
+//!
+//! Call [`write_buffer`](StagingBelt::write_buffer) any number of times per
+//! frame to record copies into the current encoder, then [`finish`](StagingBelt::finish)
+//! once the encoder is done recording. After submitting that work, await
+//! [`recall`](StagingBelt::recall) so chunks whose copies have completed are
+//! recycled for the next frame instead of allocating fresh ones.
+
+use std::future::Future;
+
+use futures::channel::oneshot;
+
+/// Rounds `size` up to the nearest multiple of `wgpu::COPY_BUFFER_ALIGNMENT`,
+/// so chunk offsets stay valid `copy_buffer_to_buffer` sources even when a
+/// chunk serves more than one `write_buffer` call.
+fn align_copy_size(size: wgpu::BufferAddress) -> wgpu::BufferAddress {
+    let alignment = wgpu::COPY_BUFFER_ALIGNMENT;
+    (size + alignment - 1) / alignment * alignment
+}
+
+struct Chunk {
+    buffer: wgpu::Buffer,
+    size: wgpu::BufferAddress,
+    offset: wgpu::BufferAddress,
+}
+
+/// A belt of fixed-size staging buffers used to stream writes into GPU
+/// buffers, avoiding per-frame buffer reallocation for dynamic geometry.
+pub struct StagingBelt {
+    chunk_size: wgpu::BufferAddress,
+    /// Chunks fully recycled and ready to be written into again.
+    free_chunks: Vec<Chunk>,
+    /// Chunks with outstanding writes this frame, not yet handed to the GPU.
+    active_chunks: Vec<Chunk>,
+    /// Chunks unmapped and submitted, waiting for their copies to complete.
+    closed_chunks: Vec<Chunk>,
+}
+
+impl StagingBelt {
+    /// Creates a belt whose chunks are each `chunk_size` bytes; pick a size
+    /// that comfortably covers a typical frame's worth of writes so most
+    /// frames are served from a single chunk.
+    pub fn new(chunk_size: wgpu::BufferAddress) -> Self {
+        StagingBelt {
+            chunk_size,
+            free_chunks: Vec::new(),
+            active_chunks: Vec::new(),
+            closed_chunks: Vec::new(),
+        }
+    }
+
+    /// Records a copy of `size` bytes into `target` at `offset` using
+    /// `encoder`, and returns a mapped view the caller writes the source
+    /// bytes into before the encoder is submitted.
+    pub fn write_buffer(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        size: wgpu::BufferAddress,
+        device: &wgpu::Device,
+    ) -> wgpu::BufferViewMut<'_> {
+        let padded_size = align_copy_size(size);
+        let chunk_index = match self
+            .active_chunks
+            .iter()
+            .position(|chunk| chunk.offset + padded_size <= chunk.size)
+        {
+            Some(index) => index,
+            None => {
+                let chunk = match self
+                    .free_chunks
+                    .iter()
+                    .position(|chunk| padded_size <= chunk.size)
+                {
+                    Some(index) => self.free_chunks.remove(index),
+                    None => {
+                        let buffer_size = padded_size.max(self.chunk_size);
+                        Chunk {
+                            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                                label: Some("staging belt chunk"),
+                                size: buffer_size,
+                                usage: wgpu::BufferUsage::MAP_WRITE | wgpu::BufferUsage::COPY_SRC,
+                                mapped_at_creation: true,
+                            }),
+                            size: buffer_size,
+                            offset: 0,
+                        }
+                    }
+                };
+                self.active_chunks.push(chunk);
+                self.active_chunks.len() - 1
+            }
+        };
+
+        let chunk = &mut self.active_chunks[chunk_index];
+        let start = chunk.offset;
+        chunk.offset += padded_size;
+        encoder.copy_buffer_to_buffer(&chunk.buffer, start, target, offset, size);
+        chunk.buffer.slice(start..start + size).get_mapped_range_mut()
+    }
+
+    /// Unmaps every chunk written this frame. Call once per frame after the
+    /// last `write_buffer`, before the encoder(s) referencing them are
+    /// submitted.
+    pub fn finish(&mut self) {
+        for chunk in self.active_chunks.drain(..) {
+            chunk.buffer.unmap();
+            self.closed_chunks.push(chunk);
+        }
+    }
+
+    /// Waits for the queue to finish the copies out of chunks closed since
+    /// the last `recall`, then returns them to the free list, re-mapped and
+    /// ready to be written into again.
+    pub fn recall(&mut self) -> impl Future<Output = ()> + Send + '_ {
+        let mut pending = Vec::with_capacity(self.closed_chunks.len());
+        for mut chunk in self.closed_chunks.drain(..) {
+            let (sender, receiver) = oneshot::channel();
+            chunk.offset = 0;
+            chunk
+                .buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Write, move |result| {
+                    let _ = sender.send(result);
+                });
+            pending.push((chunk, receiver));
+        }
+
+        async move {
+            for (chunk, receiver) in pending {
+                receiver
+                    .await
+                    .expect("staging belt chunk dropped before mapping")
+                    .expect("failed to map staging belt chunk");
+                self.free_chunks.push(chunk);
+            }
+        }
+    }
+}
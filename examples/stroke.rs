@@ -0,0 +1,352 @@
+//! CPU stroke tessellation for polylines, modeled on lyon's `StrokeTessellator`.
+//!
+//! Turns a sequence of points into a triangle mesh with a configurable width,
+//! join style and end caps, so examples can draw thick anti-aliased lines
+//! instead of relying on `PrimitiveTopology::LineList` plus MSAA alone.
+
+use bytemuck::{Pod, Zeroable};
+
+/// A single point along the polyline to be stroked.
+#[derive(Clone, Copy)]
+pub struct StrokePoint {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+    /// Clip-space depth (wgpu's `0.0` near .. `1.0` far range) carried
+    /// straight through to `gl_Position.z`, letting a stroke be depth-tested
+    /// against other geometry.
+    pub depth: f32,
+}
+
+/// How two consecutive segments are connected at an interior joint.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineJoin {
+    /// A single triangle filling the gap between the two segment edges.
+    Bevel,
+    /// The outer edges are extended until they meet, falling back to `Bevel`
+    /// once the miter length exceeds `width * limit`.
+    Miter { limit: f32 },
+    /// A fan of triangles approximating an arc around the joint.
+    Round,
+}
+
+/// How the two free ends of the polyline are terminated.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineCap {
+    /// The stroke stops exactly at the endpoint.
+    Butt,
+    /// The stroke extends by half the width past the endpoint.
+    Square,
+    /// A semicircular cap is fanned around the endpoint.
+    Round,
+}
+
+#[derive(Clone, Copy)]
+pub struct StrokeOptions {
+    pub width: f32,
+    pub join: LineJoin,
+    pub start_cap: LineCap,
+    pub end_cap: LineCap,
+}
+
+impl Default for StrokeOptions {
+    fn default() -> Self {
+        StrokeOptions {
+            width: 1.0,
+            join: LineJoin::Miter { limit: 4.0 },
+            start_cap: LineCap::Butt,
+            end_cap: LineCap::Butt,
+        }
+    }
+}
+
+/// A vertex of the tessellated stroke mesh.
+///
+/// `edge_distance` is signed and ranges over `[-1.0, 1.0]` across the width
+/// of the stroke; a fragment shader can use it to compute analytic coverage
+/// for anti-aliasing that composes with MSAA.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct StrokeVertex {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+    pub edge_distance: f32,
+    pub depth: f32,
+}
+
+unsafe impl Pod for StrokeVertex {}
+unsafe impl Zeroable for StrokeVertex {}
+
+/// The output of tessellating one or more polylines: a single vertex/index
+/// buffer pair suitable for a `PrimitiveTopology::TriangleList` draw.
+#[derive(Default)]
+pub struct StrokeMesh {
+    pub vertices: Vec<StrokeVertex>,
+    pub indices: Vec<u16>,
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn length(v: [f32; 2]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1]).sqrt()
+}
+
+fn normalize(v: [f32; 2]) -> [f32; 2] {
+    let len = length(v);
+    [v[0] / len, v[1] / len]
+}
+
+fn perp(v: [f32; 2]) -> [f32; 2] {
+    [-v[1], v[0]]
+}
+
+fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn scale(v: [f32; 2], s: f32) -> [f32; 2] {
+    [v[0] * s, v[1] * s]
+}
+
+/// Tessellates a single polyline into a stroke mesh, appending to `mesh`.
+///
+/// Zero-length segments (coincident consecutive points) are skipped, since
+/// they have no well-defined direction to offset from.
+pub fn tessellate_stroke(points: &[StrokePoint], options: &StrokeOptions, mesh: &mut StrokeMesh) {
+    // Filter out zero-length segments up front so joints are computed between
+    // genuinely distinct points.
+    let mut pts = Vec::with_capacity(points.len());
+    for p in points {
+        if pts
+            .last()
+            .map_or(true, |last: &StrokePoint| length(sub(p.position, last.position)) > std::f32::EPSILON)
+        {
+            pts.push(*p);
+        }
+    }
+    if pts.len() < 2 {
+        return;
+    }
+
+    let half_width = options.width * 0.5;
+    let segment_count = pts.len() - 1;
+    let mut directions = Vec::with_capacity(segment_count);
+    for i in 0..segment_count {
+        directions.push(normalize(sub(pts[i + 1].position, pts[i].position)));
+    }
+
+    let mut push_vertex = |position: [f32; 2], color: [f32; 4], edge_distance: f32, depth: f32| -> u16 {
+        let index = mesh.vertices.len() as u16;
+        assert!(index < u16::MAX, "stroke mesh exceeded u16::MAX vertices");
+        mesh.vertices.push(StrokeVertex {
+            position,
+            color,
+            edge_distance,
+            depth,
+        });
+        index
+    };
+
+    // Emit one quad (two triangles) per segment, plus joins and caps in between.
+    for i in 0..segment_count {
+        let normal = perp(directions[i]);
+        let p0 = pts[i];
+        let p1 = pts[i + 1];
+
+        let a0 = push_vertex(add(p0.position, scale(normal, half_width)), p0.color, 1.0, p0.depth);
+        let b0 = push_vertex(add(p0.position, scale(normal, -half_width)), p0.color, -1.0, p0.depth);
+        let a1 = push_vertex(add(p1.position, scale(normal, half_width)), p1.color, 1.0, p1.depth);
+        let b1 = push_vertex(add(p1.position, scale(normal, -half_width)), p1.color, -1.0, p1.depth);
+
+        mesh.indices.extend_from_slice(&[a0, b0, a1, a1, b0, b1]);
+
+        if i + 1 < segment_count {
+            emit_join(
+                &mut mesh.vertices,
+                &mut mesh.indices,
+                p1,
+                directions[i],
+                directions[i + 1],
+                half_width,
+                options.join,
+            );
+        }
+    }
+
+    emit_cap(
+        &mut mesh.vertices,
+        &mut mesh.indices,
+        pts[0],
+        scale(directions[0], -1.0),
+        half_width,
+        options.start_cap,
+    );
+    emit_cap(
+        &mut mesh.vertices,
+        &mut mesh.indices,
+        pts[pts.len() - 1],
+        directions[segment_count - 1],
+        half_width,
+        options.end_cap,
+    );
+}
+
+/// Fills the gap between two consecutive segments at their shared joint.
+fn emit_join(
+    vertices: &mut Vec<StrokeVertex>,
+    indices: &mut Vec<u16>,
+    joint: StrokePoint,
+    dir_in: [f32; 2],
+    dir_out: [f32; 2],
+    half_width: f32,
+    join: LineJoin,
+) {
+    let normal_in = perp(dir_in);
+    let normal_out = perp(dir_out);
+    // Cross product (z component) tells us which side is the outer corner.
+    let turn = dir_in[0] * dir_out[1] - dir_in[1] * dir_out[0];
+    let side = if turn >= 0.0 { -1.0 } else { 1.0 };
+
+    let mut push = |position: [f32; 2], edge_distance: f32| -> u16 {
+        let index = vertices.len() as u16;
+        assert!(index < u16::MAX, "stroke mesh exceeded u16::MAX vertices");
+        vertices.push(StrokeVertex {
+            position,
+            color: joint.color,
+            edge_distance,
+            depth: joint.depth,
+        });
+        index
+    };
+
+    // `center` sits exactly on the centerline, like `emit_cap`'s round-cap
+    // center; only the outer rim vertices are a full `half_width` away.
+    let center = push(joint.position, 0.0);
+    let outer_in = push(add(joint.position, scale(normal_in, half_width * side)), side);
+    let outer_out = push(add(joint.position, scale(normal_out, half_width * side)), side);
+
+    match join {
+        LineJoin::Bevel => {
+            indices.extend_from_slice(&[center, outer_in, outer_out]);
+        }
+        LineJoin::Miter { limit } => {
+            let bisector = normalize(add(normal_in, normal_out));
+            let cos_half_angle = bisector[0] * normal_in[0] + bisector[1] * normal_in[1];
+            let miter_length = if cos_half_angle.abs() > std::f32::EPSILON {
+                1.0 / cos_half_angle
+            } else {
+                f32::INFINITY
+            };
+            if miter_length.abs() <= limit {
+                // The tip is `miter_length` half-widths out from the
+                // centerline, not one, so its edge distance must scale the
+                // same way or the miter triangle shades like the rest of
+                // the join instead of fading out past the rim.
+                let tip = push(
+                    add(joint.position, scale(bisector, half_width * miter_length * side)),
+                    side * miter_length,
+                );
+                indices.extend_from_slice(&[center, outer_in, tip, center, tip, outer_out]);
+            } else {
+                indices.extend_from_slice(&[center, outer_in, outer_out]);
+            }
+        }
+        LineJoin::Round => {
+            const SEGMENTS: usize = 8;
+            let start_angle = normal_in[1].atan2(normal_in[0]);
+            let mut end_angle = normal_out[1].atan2(normal_out[0]);
+            if side < 0.0 {
+                while end_angle > start_angle {
+                    end_angle -= std::f32::consts::TAU;
+                }
+            } else {
+                while end_angle < start_angle {
+                    end_angle += std::f32::consts::TAU;
+                }
+            }
+            let mut prev = outer_in;
+            for step in 1..=SEGMENTS {
+                let t = step as f32 / SEGMENTS as f32;
+                let angle = start_angle + (end_angle - start_angle) * t;
+                let point = if step == SEGMENTS {
+                    outer_out
+                } else {
+                    push(
+                        add(joint.position, scale([angle.cos(), angle.sin()], half_width)),
+                        side,
+                    )
+                };
+                indices.extend_from_slice(&[center, prev, point]);
+                prev = point;
+            }
+        }
+    }
+}
+
+/// Terminates one end of the polyline. `outward` points away from the body
+/// of the stroke (i.e. opposite the segment direction at the start cap).
+fn emit_cap(
+    vertices: &mut Vec<StrokeVertex>,
+    indices: &mut Vec<u16>,
+    end: StrokePoint,
+    outward: [f32; 2],
+    half_width: f32,
+    cap: LineCap,
+) {
+    if cap == LineCap::Butt {
+        return;
+    }
+
+    let normal = perp(outward);
+    let mut push = |position: [f32; 2], edge_distance: f32| -> u16 {
+        let index = vertices.len() as u16;
+        assert!(index < u16::MAX, "stroke mesh exceeded u16::MAX vertices");
+        vertices.push(StrokeVertex {
+            position,
+            color: end.color,
+            edge_distance,
+            depth: end.depth,
+        });
+        index
+    };
+
+    let left = push(add(end.position, scale(normal, half_width)), 1.0);
+    let right = push(add(end.position, scale(normal, -half_width)), -1.0);
+
+    match cap {
+        LineCap::Butt => unreachable!(),
+        LineCap::Square => {
+            let far_left = push(
+                add(add(end.position, scale(normal, half_width)), scale(outward, half_width)),
+                1.0,
+            );
+            let far_right = push(
+                add(add(end.position, scale(normal, -half_width)), scale(outward, half_width)),
+                -1.0,
+            );
+            indices.extend_from_slice(&[left, right, far_right, left, far_right, far_left]);
+        }
+        LineCap::Round => {
+            const SEGMENTS: usize = 8;
+            let start_angle = normal[1].atan2(normal[0]);
+            let end_angle = start_angle - std::f32::consts::PI;
+            let center = push(end.position, 0.0);
+            let mut prev = left;
+            for step in 1..=SEGMENTS {
+                let t = step as f32 / SEGMENTS as f32;
+                let angle = start_angle + (end_angle - start_angle) * t;
+                let point = if step == SEGMENTS {
+                    right
+                } else {
+                    push(
+                        add(end.position, scale([angle.cos(), angle.sin()], half_width)),
+                        1.0 - 2.0 * t,
+                    )
+                };
+                indices.extend_from_slice(&[center, prev, point]);
+                prev = point;
+            }
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! A small glyph-brush style text overlay: queue [`Section`]s each frame and
+//! [`TextRenderer::draw`] rasterizes any glyphs not already in the atlas,
+//! builds a quad per glyph, and renders them in a final alpha-blended pass
+//! over whatever is already in the target view.
+//!
+//! The per-frame glyph quads are streamed through a [`StagingBelt`], since
+//! the quad count changes every frame but the vertex buffer doesn't need to
+//! be reallocated for it. Atlas cache misses are rare (once per glyph/size
+//! ever seen) and texture-shaped, so those go through `Queue::write_texture`
+//! directly instead.
+
+use std::collections::HashMap;
+
+use bytemuck::{Pod, Zeroable};
+use rusttype::{Font, Scale};
+
+use super::staging_belt::StagingBelt;
+
+const ATLAS_SIZE: u32 = 512;
+
+/// A string to draw at a screen position, in physical pixels from the
+/// top-left of the target.
+pub struct Section<'a> {
+    pub text: &'a str,
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+    pub scale: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GlyphVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+unsafe impl Pod for GlyphVertex {}
+unsafe impl Zeroable for GlyphVertex {}
+
+/// Where a rasterized glyph landed in the atlas, plus the metrics needed to
+/// place its quad relative to the pen position.
+struct AtlasEntry {
+    uv_min: [f32; 2],
+    uv_max: [f32; 2],
+    size: [f32; 2],
+    offset: [f32; 2],
+    advance: f32,
+}
+
+struct QueuedSection {
+    text: String,
+    position: [f32; 2],
+    color: [f32; 4],
+    scale: f32,
+}
+
+/// Rasterizes queued [`Section`]s into a dynamic atlas and draws them as
+/// textured quads.
+pub struct TextRenderer {
+    font: Font<'static>,
+    atlas_texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    vertex_buffer_capacity: wgpu::BufferAddress,
+    glyphs: HashMap<(char, u32), AtlasEntry>,
+    cursor: (u32, u32),
+    shelf_height: u32,
+    sections: Vec<QueuedSection>,
+}
+
+impl TextRenderer {
+    /// Loads `font_bytes` as a TTF/OTF font and sets up the atlas, pipeline
+    /// and bind group used to draw text over a `format` target.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, font_bytes: &'static [u8]) -> Self {
+        let font = Font::try_from_bytes(font_bytes).expect("invalid font data");
+
+        let atlas_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("glyph atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let atlas_view = atlas_texture.create_default_view();
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            compare: None,
+            anisotropy_clamp: None,
+            label: None,
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("text screen size"),
+            size: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry::new(
+                    0,
+                    wgpu::ShaderStage::VERTEX,
+                    wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: None,
+                    },
+                ),
+                wgpu::BindGroupLayoutEntry::new(
+                    1,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::SampledTexture {
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                        multisampled: false,
+                    },
+                ),
+                wgpu::BindGroupLayoutEntry::new(
+                    2,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::Sampler { comparison: false },
+                ),
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::Binding {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+                },
+                wgpu::Binding {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::Binding {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: &[&bind_group_layout],
+        });
+
+        let vs_bytes = include_bytes!("text.vert.spv");
+        let fs_bytes = include_bytes!("text.frag.spv");
+        let vs_module = device
+            .create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(&vs_bytes[..])).unwrap());
+        let fs_module = device
+            .create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(&fs_bytes[..])).unwrap());
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: &pipeline_layout,
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                depth_bias: 0,
+                depth_bias_slope_scale: 0.0,
+                depth_bias_clamp: 0.0,
+            }),
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            color_states: &[wgpu::ColorStateDescriptor {
+                format,
+                color_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            depth_stencil_state: None,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: &[wgpu::VertexBufferDescriptor {
+                    stride: std::mem::size_of::<GlyphVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float2, 1 => Float2, 2 => Float4],
+                }],
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        });
+
+        let vertex_buffer_capacity = 0;
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("glyph vertices"),
+            size: 0,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        TextRenderer {
+            font,
+            atlas_texture,
+            bind_group,
+            uniform_buffer,
+            pipeline,
+            vertex_buffer,
+            vertex_buffer_capacity,
+            glyphs: HashMap::new(),
+            cursor: (0, 0),
+            shelf_height: 0,
+            sections: Vec::new(),
+        }
+    }
+
+    /// Queues a section of text to be drawn on the next `draw` call.
+    pub fn queue(&mut self, section: Section) {
+        self.sections.push(QueuedSection {
+            text: section.text.to_owned(),
+            position: section.position,
+            color: section.color,
+            scale: section.scale,
+        });
+    }
+
+    /// Looks up (rasterizing and caching into the atlas if necessary) the
+    /// placement of `c` at `scale`, or `None` if the glyph is blank (e.g.
+    /// whitespace) or the atlas has run out of room for it.
+    fn glyph_entry(&mut self, queue: &wgpu::Queue, c: char, scale: f32) -> Option<&AtlasEntry> {
+        let px = scale.round().max(1.0) as u32;
+        let key = (c, px);
+        if !self.glyphs.contains_key(&key) {
+            let scaled = self.font.glyph(c).scaled(Scale::uniform(px as f32));
+            let h_metrics = scaled.h_metrics();
+            let glyph = scaled.positioned(rusttype::point(0.0, 0.0));
+
+            let (bitmap, size, offset) = match glyph.pixel_bounding_box() {
+                Some(bounds) => {
+                    let width = (bounds.max.x - bounds.min.x).max(0) as u32;
+                    let height = (bounds.max.y - bounds.min.y).max(0) as u32;
+                    let mut bitmap = vec![0u8; (width * height) as usize];
+                    glyph.draw(|x, y, v| {
+                        bitmap[(y * width + x) as usize] = (v * 255.0) as u8;
+                    });
+                    (
+                        bitmap,
+                        [width as f32, height as f32],
+                        [bounds.min.x as f32, bounds.min.y as f32],
+                    )
+                }
+                // Whitespace and other glyphs with no ink still advance the
+                // pen, but have nothing to rasterize.
+                None => (Vec::new(), [0.0, 0.0], [0.0, 0.0]),
+            };
+
+            let uv = if bitmap.is_empty() {
+                [0.0, 0.0, 0.0, 0.0]
+            } else {
+                let (width, height) = (size[0] as u32, size[1] as u32);
+                if self.cursor.0 + width > ATLAS_SIZE {
+                    self.cursor = (0, self.cursor.1 + self.shelf_height);
+                    self.shelf_height = 0;
+                }
+                if self.cursor.1 + height > ATLAS_SIZE {
+                    log::warn!("glyph atlas is full, dropping glyph {:?}", c);
+                    [0.0, 0.0, 0.0, 0.0]
+                } else {
+                    let (x, y) = self.cursor;
+                    queue.write_texture(
+                        wgpu::TextureCopyView {
+                            texture: &self.atlas_texture,
+                            mip_level: 0,
+                            origin: wgpu::Origin3d { x, y, z: 0 },
+                        },
+                        &bitmap,
+                        wgpu::TextureDataLayout {
+                            offset: 0,
+                            bytes_per_row: width,
+                            rows_per_image: height,
+                        },
+                        wgpu::Extent3d {
+                            width,
+                            height,
+                            depth: 1,
+                        },
+                    );
+                    self.cursor.0 += width;
+                    self.shelf_height = self.shelf_height.max(height);
+                    [
+                        x as f32 / ATLAS_SIZE as f32,
+                        y as f32 / ATLAS_SIZE as f32,
+                        (x + width) as f32 / ATLAS_SIZE as f32,
+                        (y + height) as f32 / ATLAS_SIZE as f32,
+                    ]
+                }
+            };
+
+            self.glyphs.insert(
+                key,
+                AtlasEntry {
+                    uv_min: [uv[0], uv[1]],
+                    uv_max: [uv[2], uv[3]],
+                    size,
+                    offset,
+                    advance: h_metrics.advance_width,
+                },
+            );
+        }
+        self.glyphs.get(&key)
+    }
+
+    /// Builds the glyph quads for every queued section, streams them into
+    /// the vertex buffer through `belt`, and draws them over `target` in an
+    /// alpha-blended pass that loads (rather than clears) its contents.
+    ///
+    /// The caller is responsible for calling `belt.finish()` once all of a
+    /// frame's writes (this one included) have been recorded.
+    pub fn draw(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        belt: &mut StagingBelt,
+        target: &wgpu::TextureView,
+        screen_size: [f32; 2],
+    ) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&screen_size));
+
+        let sections = std::mem::take(&mut self.sections);
+        let mut vertices: Vec<GlyphVertex> = Vec::new();
+        for section in &sections {
+            let mut pen = section.position;
+            for c in section.text.chars() {
+                if c == '\n' {
+                    pen[0] = section.position[0];
+                    pen[1] += section.scale;
+                    continue;
+                }
+                let advance = match self.glyph_entry(queue, c, section.scale) {
+                    Some(entry) if entry.size[0] > 0.0 && entry.size[1] > 0.0 => {
+                        let min = [pen[0] + entry.offset[0], pen[1] + entry.offset[1]];
+                        let max = [min[0] + entry.size[0], min[1] + entry.size[1]];
+                        let (uv_min, uv_max) = (entry.uv_min, entry.uv_max);
+                        vertices.extend_from_slice(&[
+                            GlyphVertex { position: [min[0], min[1]], tex_coords: [uv_min[0], uv_min[1]], color: section.color },
+                            GlyphVertex { position: [max[0], min[1]], tex_coords: [uv_max[0], uv_min[1]], color: section.color },
+                            GlyphVertex { position: [max[0], max[1]], tex_coords: [uv_max[0], uv_max[1]], color: section.color },
+                            GlyphVertex { position: [min[0], min[1]], tex_coords: [uv_min[0], uv_min[1]], color: section.color },
+                            GlyphVertex { position: [max[0], max[1]], tex_coords: [uv_max[0], uv_max[1]], color: section.color },
+                            GlyphVertex { position: [min[0], max[1]], tex_coords: [uv_min[0], uv_max[1]], color: section.color },
+                        ]);
+                        entry.advance
+                    }
+                    Some(entry) => entry.advance,
+                    None => 0.0,
+                };
+                pen[0] += advance;
+            }
+        }
+
+        if vertices.is_empty() {
+            return;
+        }
+
+        let size = (vertices.len() * std::mem::size_of::<GlyphVertex>()) as wgpu::BufferAddress;
+        if size > self.vertex_buffer_capacity {
+            self.vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("glyph vertices"),
+                size,
+                usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            });
+            self.vertex_buffer_capacity = size;
+        }
+
+        {
+            let mut view = belt.write_buffer(encoder, &self.vertex_buffer, 0, size, device);
+            view.copy_from_slice(bytemuck::cast_slice(&vertices));
+        }
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: target,
+                resolve_target: None,
+                load_op: wgpu::LoadOp::Load,
+                store_op: wgpu::StoreOp::Store,
+                clear_color: wgpu::Color::BLACK,
+            }],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(0..size));
+        rpass.draw(0..vertices.len() as u32, 0..1);
+    }
+}